@@ -1,10 +1,11 @@
 //! YCSB benchmarking utility.
 
 use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread;
 use std::time::Duration;
 
-use color_print::cprintln;
+use color_print::{ceprintln, cprintln};
 
 use clap::Parser;
 
@@ -15,9 +16,48 @@ const VALID_WORKLOADS: [char; 6] = ['a', 'b', 'c', 'd', 'e', 'f'];
 const RESP_TIMEOUT: Duration = Duration::from_secs(60);
 const YCSB_TIMEOUT: Duration = Duration::from_secs(600);
 
+/// Set by the SIGINT handler on first Ctrl-C; polled by `feeder_thread` so
+/// feeders can stop cleanly and hand back their partial `Stats`. A second
+/// SIGINT bypasses this and force-kills the process immediately.
+pub(crate) static STOP: AtomicBool = AtomicBool::new(false);
+static SIGINT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Install a Ctrl-C handler: first SIGINT sets `STOP` so in-flight feeders
+/// wind down gracefully, second SIGINT kills the process right away.
+fn install_sigint_handler() {
+    ctrlc::set_handler(|| {
+        if SIGINT_COUNT.fetch_add(1, Ordering::SeqCst) + 1 >= 2 {
+            std::process::exit(130);
+        }
+        STOP.store(true, Ordering::SeqCst);
+    })
+    .expect("failed to install SIGINT handler");
+}
+
+mod memstats;
+mod workload;
 mod ycsb;
 use ycsb::*;
 
+/// Percentile of a *sorted* sample vector (`pct` in `[0, 100]`).
+fn percentile_of_sorted(sorted: &[f64], pct: f64) -> f64 {
+    let len = sorted.len();
+    let idx = ((len as f64) * (pct / 100.0)).ceil() as usize;
+    sorted[idx.saturating_sub(1).min(len - 1)]
+}
+
+/// Summary statistics for a latency sample vector: avg/min/max/median/
+/// stddev plus a caller-chosen percentile list.
+#[derive(Debug, Clone)]
+struct LatencySummary {
+    avg: f64,
+    min: f64,
+    max: f64,
+    median: f64,
+    stddev: f64,
+    percentiles: Vec<(f64, f64)>,
+}
+
 /// Per-client performance statistics recording.
 struct Stats {
     /// Number of client stats merged into this struct.
@@ -25,6 +65,10 @@ struct Stats {
     // Performance statistics:
     total_ms: f64,                          // in millisecs
     lat_samples: HashMap<String, Vec<f64>>, // map from op type -> microsecs
+    // Per-op median latency contributed by each merged client, kept
+    // separately from `lat_samples` so `median_run` reports a robust
+    // cross-client aggregate instead of a statistic over the pooled samples.
+    client_medians: HashMap<String, Vec<f64>>,
 }
 
 impl Stats {
@@ -33,6 +77,7 @@ impl Stats {
             merged: 0,
             total_ms: 0.0,
             lat_samples: HashMap::new(),
+            client_medians: HashMap::new(),
         }
     }
 
@@ -47,7 +92,9 @@ impl Stats {
         self.lat_samples.values().map(|s| s.len()).sum()
     }
 
-    fn latency_stats(samples: &[f64]) -> Option<(f64, f64, f64, f64)> {
+    /// Compute avg/min/max/median/stddev and the requested `percentiles`
+    /// over `samples` (order not assumed).
+    fn summarize(samples: &[f64], percentiles: &[f64]) -> Option<LatencySummary> {
         if samples.is_empty() {
             return None;
         }
@@ -57,15 +104,51 @@ impl Stats {
         let len = sorted.len();
         let sum: f64 = sorted.iter().sum();
         let avg = sum / len as f64;
-        let min = sorted[0];
-        let max = sorted[len - 1];
-        let idx = ((len as f64) * 0.99).ceil() as usize;
-        let idx = idx.saturating_sub(1).min(len - 1);
-        let p99 = sorted[idx];
-        Some((avg, min, max, p99))
+        let variance = sorted.iter().map(|x| (x - avg).powi(2)).sum::<f64>() / len as f64;
+        Some(LatencySummary {
+            avg,
+            min: sorted[0],
+            max: sorted[len - 1],
+            median: percentile_of_sorted(&sorted, 50.0),
+            stddev: variance.sqrt(),
+            percentiles: percentiles
+                .iter()
+                .map(|&p| (p, percentile_of_sorted(&sorted, p)))
+                .collect(),
+        })
+    }
+
+    /// MAD-based outlier trim: discard samples more than `k` median-absolute-
+    /// deviations away from the median. If the MAD is zero, every sample is
+    /// kept.
+    fn trim_outliers(samples: &[f64], k: f64) -> Vec<f64> {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = percentile_of_sorted(&sorted, 50.0);
+
+        let mut devs: Vec<f64> = sorted.iter().map(|x| (x - median).abs()).collect();
+        devs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mad = percentile_of_sorted(&devs, 50.0);
+        if mad == 0.0 {
+            return samples.to_vec();
+        }
+
+        samples
+            .iter()
+            .copied()
+            .filter(|x| (x - median).abs() <= k * mad)
+            .collect()
     }
 
-    fn print(&self, phase: &str) {
+    /// Median of the per-client medians recorded for `op`: a robust,
+    /// "smoothed" cross-client aggregate for `num_clis > 1` runs, as opposed
+    /// to a statistic over the naively concatenated sample pool.
+    fn median_run(&self, op: &str) -> Option<f64> {
+        let medians = self.client_medians.get(op)?;
+        Self::summarize(medians, &[]).map(|s| s.median)
+    }
+
+    fn print(&self, phase: &str, percentiles: &[f64], trim_k: Option<f64>) {
         cprintln!(
             "  <cyan>{:6}</>  {:6.0} ms",
             format!("[{}]", phase),
@@ -83,28 +166,99 @@ impl Stats {
         ops.sort();
         for (i, op) in ops.iter().enumerate() {
             let samples = &self.lat_samples[op];
-            let (avg, min, max, p99) = Self::latency_stats(samples).unwrap_or((0.0, 0.0, 0.0, 0.0));
-            if i == 0 {
-                print!("    Latency:");
+            let summary = Self::summarize(samples, percentiles);
+            let tag = if i == 0 {
+                "    Latency:"
             } else {
-                print!("            ");
+                "            "
+            };
+            match &summary {
+                Some(s) => println!(
+                    "{}    {:6}  ops {:6}  avg {:9.2}  median {:8.2}  stddev {:9.2}  min {:6.0}  max {:6.0}  us",
+                    tag, op, samples.len(), s.avg, s.median, s.stddev, s.min, s.max
+                ),
+                None => println!("{}    {:6}  ops {:6}", tag, op, samples.len()),
+            }
+            if let Some(s) = &summary {
+                let pcts: Vec<String> = s
+                    .percentiles
+                    .iter()
+                    .map(|(p, v)| format!("p{:<5} {:7.0}", p, v))
+                    .collect();
+                println!("                    {}  us", pcts.join("  "));
+            }
+
+            if let Some(k) = trim_k {
+                let trimmed = Self::trim_outliers(samples, k);
+                let dropped = samples.len() - trimmed.len();
+                if let Some(ts) = Self::summarize(&trimmed, percentiles) {
+                    println!(
+                        "                    trimmed ({} outliers dropped, k={}): avg {:9.2}  median {:8.2}  stddev {:9.2}  us",
+                        dropped, k, ts.avg, ts.median, ts.stddev
+                    );
+                }
+            }
+
+            if self.merged > 1 {
+                if let Some(mr) = self.median_run(op) {
+                    println!(
+                        "                    median-of-{}-clients: {:8.2} us",
+                        self.merged, mr
+                    );
+                }
             }
-            println!(
-                "    {:6}  ops {:6}  avg {:9.2}  min {:6.0}  max {:6.0}  p99 {:6.0}  us",
-                op,
-                samples.len(),
-                avg,
-                min,
-                max,
-                p99
-            );
         }
     }
 
+    /// CSV header matching the columns produced by `to_csv`.
+    const CSV_HEADER: &'static str =
+        "phase,op,count,avg_us,median_us,stddev_us,min_us,max_us,p99_us,throughput";
+
+    /// One CSV row per op type: phase, op, count, avg/median/stddev/min/max/
+    /// p99 latency in microsecs, and that op's throughput in ops/sec.
+    fn to_csv(&self, phase: &str) -> Vec<String> {
+        let mut ops: Vec<_> = self.lat_samples.keys().cloned().collect();
+        ops.sort();
+        ops.into_iter()
+            .map(|op| {
+                let samples = &self.lat_samples[&op];
+                let s = Self::summarize(samples, &[99.0]);
+                let (avg, median, stddev, min, max, p99) = match &s {
+                    Some(s) => (s.avg, s.median, s.stddev, s.min, s.max, s.percentiles[0].1),
+                    None => (0.0, 0.0, 0.0, 0.0, 0.0, 0.0),
+                };
+                let tput = if self.total_ms > 0.0 {
+                    samples.len() as f64 / (self.total_ms / 1000.0)
+                } else {
+                    0.0
+                };
+                format!(
+                    "{},{},{},{:.2},{:.2},{:.2},{:.0},{:.0},{:.0},{:.2}",
+                    phase,
+                    op,
+                    samples.len(),
+                    avg,
+                    median,
+                    stddev,
+                    min,
+                    max,
+                    p99,
+                    tput
+                )
+            })
+            .collect()
+    }
+
     /// Merge with another stats struct, taking reasonable arithmetics on the
     /// stats fields.
     fn merge(&mut self, other: Stats) {
         debug_assert_ne!(other.merged, 0);
+        let other_medians: HashMap<String, f64> = other
+            .lat_samples
+            .iter()
+            .filter_map(|(op, samples)| Some((op.clone(), Self::summarize(samples, &[])?.median)))
+            .collect();
+
         if self.merged == 0 {
             *self = other;
         } else {
@@ -115,6 +269,9 @@ impl Stats {
             }
             self.merged += other.merged;
         }
+        for (op, median) in other_medians {
+            self.client_medians.entry(op).or_default().push(median);
+        }
     }
 }
 
@@ -125,6 +282,11 @@ fn ycsb_bench(
     load: bool,
     ikeys: BTreeSet<String>,
 ) -> Result<(Stats, BTreeSet<String>), RunnerError> {
+    let open_loop = args.target_rate.map(|target_rate| OpenLoop {
+        target_rate,
+        max_inflight: args.max_inflight,
+    });
+
     let mut drivers = vec![];
     for client in clients {
         drivers.push(YcsbDriver::exec(
@@ -133,9 +295,10 @@ fn ycsb_bench(
             load,
             client,
             ikeys.clone(),
+            open_loop,
         )?);
     }
-    println!("  Launched {} YCSB drivers, now waiting...", drivers.len());
+    eprintln!("  Launched {} YCSB drivers, now waiting...", drivers.len());
 
     let mut stats = Stats::new();
     let mut ikeys = BTreeSet::new();
@@ -151,6 +314,16 @@ fn ycsb_bench(
     Ok((stats, ikeys))
 }
 
+/// Result report output format.
+#[derive(clap::ValueEnum, Clone, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable report (the default).
+    Text,
+    /// One CSV row per phase/op, suitable for archiving and `--baseline`
+    /// comparison in CI.
+    Csv,
+}
+
 /// Launcher utility arguments.
 #[derive(Parser, Debug)]
 struct Args {
@@ -169,13 +342,210 @@ struct Args {
     /// Client `just` invocation arguments.
     #[arg(long, num_args(1..))]
     client_just_args: Vec<String>,
+
+    /// Result report output format.
+    #[arg(long, value_enum, default_value = "text")]
+    output: OutputFormat,
+
+    /// Path to a prior `--output csv` report to compare this run against,
+    /// flagging any op whose p99 or throughput regressed past `--threshold`.
+    #[arg(long)]
+    baseline: Option<String>,
+
+    /// Percentage regression threshold for `--baseline` comparison.
+    #[arg(long, default_value = "5.0")]
+    threshold: f64,
+
+    /// Open-loop mode: target issue rate in ops/sec. Paces operations
+    /// against a fixed schedule instead of waiting for each response before
+    /// issuing the next, so tail latency isn't hidden by coordinated
+    /// omission. Omit for the default closed-loop behavior.
+    #[arg(long)]
+    target_rate: Option<f64>,
+
+    /// Max outstanding in-flight operations when `--target-rate` is set (a
+    /// READMODIFYWRITE operation counts once but issues two calls).
+    #[arg(long, default_value = "16")]
+    max_inflight: usize,
+
+    /// Comma-separated percentile list reported alongside avg/median/stddev
+    /// (e.g. "50,90,95,99,99.9").
+    #[arg(long, default_value = "50,90,95,99,99.9")]
+    percentiles: String,
+
+    /// Discard samples more than `--trim-k` median-absolute-deviations from
+    /// the median, and report both trimmed and untrimmed summaries.
+    #[arg(long)]
+    trim_outliers: bool,
+
+    /// MAD multiplier used by `--trim-outliers`.
+    #[arg(long, default_value = "5.0")]
+    trim_k: f64,
+
+    /// Report each phase's allocated/resident memory delta and peak
+    /// (requires the `mem-stats` cargo feature; a no-op without it).
+    #[arg(long)]
+    mem_stats: bool,
+}
+
+impl Args {
+    /// Parse `--percentiles` into a sorted percentage list.
+    fn parse_percentiles(&self) -> Result<Vec<f64>, RunnerError> {
+        let mut percentiles: Vec<f64> = self
+            .percentiles
+            .split(',')
+            .map(|s| {
+                s.trim()
+                    .parse::<f64>()
+                    .map_err(|_| RunnerError::Parse(format!("bad percentile: {}", s)))
+            })
+            .collect::<Result<_, _>>()?;
+        percentiles.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Ok(percentiles)
+    }
+}
+
+/// One parsed row of a prior `--output csv` report, keyed by (phase, op).
+struct BaselineRow {
+    p99_us: f64,
+    throughput: f64,
+}
+
+/// Parse a CSV report written by `Stats::to_csv` into a (phase, op) map.
+fn parse_baseline(path: &str) -> Result<HashMap<(String, String), BaselineRow>, RunnerError> {
+    let content = std::fs::read_to_string(path).map_err(|e| RunnerError::Io(e.to_string()))?;
+    let mut rows = HashMap::new();
+    for line in content.lines().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cols: Vec<&str> = line.split(',').collect();
+        if cols.len() != 10 {
+            return Err(RunnerError::Parse(format!(
+                "malformed baseline row: {}",
+                line
+            )));
+        }
+        let p99_us: f64 = cols[8]
+            .parse()
+            .map_err(|_| RunnerError::Parse(format!("bad p99_us in baseline row: {}", line)))?;
+        let throughput: f64 = cols[9]
+            .parse()
+            .map_err(|_| RunnerError::Parse(format!("bad throughput in baseline row: {}", line)))?;
+        rows.insert(
+            (cols[0].to_string(), cols[1].to_string()),
+            BaselineRow { p99_us, throughput },
+        );
+    }
+    Ok(rows)
+}
+
+/// Percentage change from `base` to `new`, positive meaning `new` grew.
+fn pct_change(base: f64, new: f64) -> f64 {
+    if base == 0.0 {
+        0.0
+    } else {
+        (new - base) / base * 100.0
+    }
+}
+
+/// Compare freshly-generated CSV `rows` against a baseline report, printing
+/// a diff table. Returns `true` if any op's p99 latency regressed (grew) or
+/// throughput regressed (shrank) past `threshold_pct`.
+fn compare_to_baseline(
+    rows: &[String],
+    baseline_path: &str,
+    threshold_pct: f64,
+) -> Result<bool, RunnerError> {
+    let baseline = parse_baseline(baseline_path)?;
+    ceprintln!(
+        "<s><yellow>Comparing against baseline:</></> {}  <magenta>(threshold {:.1}%)</>",
+        baseline_path,
+        threshold_pct
+    );
+
+    let mut regressed = false;
+    for row in rows {
+        let cols: Vec<&str> = row.split(',').collect();
+        let (phase, op) = (cols[0].to_string(), cols[1].to_string());
+        let p99: f64 = cols[8].parse().unwrap_or(0.0);
+        let tput: f64 = cols[9].parse().unwrap_or(0.0);
+        let Some(base) = baseline.get(&(phase.clone(), op.clone())) else {
+            continue;
+        };
+
+        let p99_delta = pct_change(base.p99_us, p99);
+        let tput_delta = pct_change(base.throughput, tput);
+        let p99_bad = p99_delta > threshold_pct;
+        let tput_bad = -tput_delta > threshold_pct;
+        regressed |= p99_bad || tput_bad;
+
+        ceprintln!(
+            "  <cyan>{:6}</> {:16}  p99 {:7.0} -> {:7.0} us ({:+.1}%){}",
+            phase,
+            op,
+            base.p99_us,
+            p99,
+            p99_delta,
+            if p99_bad { "  <red>REGRESSION</>" } else { "" }
+        );
+        ceprintln!(
+            "          {:16}  throughput {:9.2} -> {:9.2} ops/sec ({:+.1}%){}",
+            "",
+            base.throughput,
+            tput,
+            tput_delta,
+            if tput_bad { "  <red>REGRESSION</>" } else { "" }
+        );
+    }
+    Ok(regressed)
+}
+
+/// Print the final benchmark report in the configured `--output` format,
+/// and run the `--baseline` comparison gate if requested.
+fn report(args: &Args, phases: &[(&str, &Stats)]) -> Result<(), RunnerError> {
+    let percentiles = args.parse_percentiles()?;
+    let trim_k = args.trim_outliers.then_some(args.trim_k);
+
+    let mut csv_rows = vec![];
+    match args.output {
+        OutputFormat::Text => {
+            cprintln!(
+                "<s><yellow>Benchmarking results:</></>  <cyan>YCSB-{}</>  <magenta>{} clients</>",
+                args.workload,
+                args.num_clis
+            );
+            for (phase, stats) in phases {
+                stats.print(phase, &percentiles, trim_k);
+                csv_rows.extend(stats.to_csv(phase));
+            }
+        }
+        OutputFormat::Csv => {
+            println!("{}", Stats::CSV_HEADER);
+            for (phase, stats) in phases {
+                let rows = stats.to_csv(phase);
+                for row in &rows {
+                    println!("{}", row);
+                }
+                csv_rows.extend(rows);
+            }
+        }
+    }
+
+    if let Some(baseline_path) = &args.baseline {
+        if compare_to_baseline(&csv_rows, baseline_path, args.threshold)? {
+            return Err(RunnerError::Io("regression exceeds --threshold".into()));
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), RunnerError> {
     let args = Args::parse();
-    cprintln!("<s><yellow>YCSB benchmark configuration:</></> {:#?}", args);
+    ceprintln!("<s><yellow>YCSB benchmark configuration:</></> {:#?}", args);
     assert_ne!(args.num_clis, 0);
     assert!(VALID_WORKLOADS.contains(&args.workload));
+    install_sigint_handler();
 
     // YCSB benchmark load phase
     let (stats_load, ikeys_load) = {
@@ -191,10 +561,20 @@ fn main() -> Result<(), RunnerError> {
         thread::sleep(Duration::from_secs(
             (0.3 * args.num_clis as f64).ceil() as u64
         ));
-        cprintln!("<s><yellow>Benchmarking [Load] phase...</></>");
+        ceprintln!("<s><yellow>Benchmarking [Load] phase...</></>");
 
-        ycsb_bench(&args, clients_load, true, BTreeSet::new())?
+        let (result, mem) = memstats::track(args.mem_stats, || {
+            ycsb_bench(&args, clients_load, true, BTreeSet::new())
+        });
+        if let Some(mem) = &mem {
+            mem.print("Load");
+        }
+        result?
     };
+    if STOP.load(Ordering::Relaxed) {
+        ceprintln!("<s><red>Interrupted, reporting partial [Load] stats...</></>");
+        return report(&args, &[("Load", &stats_load)]);
+    }
 
     // YCSB benchmark run phase
     let (stats_run, _) = {
@@ -210,17 +590,53 @@ fn main() -> Result<(), RunnerError> {
         thread::sleep(Duration::from_secs(
             (0.3 * args.num_clis as f64).ceil() as u64
         ));
-        cprintln!("<s><yellow>Benchmarking [Run] phase...</></>");
+        ceprintln!("<s><yellow>Benchmarking [Run] phase...</></>");
 
-        ycsb_bench(&args, clients_run, false, ikeys_load)?
+        let (result, mem) = memstats::track(args.mem_stats, || {
+            ycsb_bench(&args, clients_run, false, ikeys_load)
+        });
+        if let Some(mem) = &mem {
+            mem.print("Run");
+        }
+        result?
     };
 
-    cprintln!(
-        "<s><yellow>Benchmarking results:</></>  <cyan>YCSB-{}</>  <magenta>{} clients</>",
-        args.workload,
-        args.num_clis
-    );
-    stats_load.print("Load");
-    stats_run.print("Run");
-    Ok(())
+    report(&args, &[("Load", &stats_load), ("Run", &stats_run)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarize_reports_min_max_median_stddev() {
+        let samples = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let summary = Stats::summarize(&samples, &[90.0]).unwrap();
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 5.0);
+        assert_eq!(summary.median, 3.0);
+        assert_eq!(summary.avg, 3.0);
+        assert!((summary.stddev - 1.4142135623730951).abs() < 1e-9);
+        assert_eq!(summary.percentiles, vec![(90.0, 5.0)]);
+    }
+
+    #[test]
+    fn summarize_of_empty_samples_is_none() {
+        assert!(Stats::summarize(&[], &[50.0]).is_none());
+    }
+
+    #[test]
+    fn trim_outliers_drops_points_far_from_the_median() {
+        let samples = vec![10.0, 11.0, 9.0, 10.0, 1000.0];
+        let trimmed = Stats::trim_outliers(&samples, 3.0);
+        assert!(!trimmed.contains(&1000.0));
+        assert_eq!(trimmed.len(), 4);
+    }
+
+    #[test]
+    fn trim_outliers_is_a_no_op_when_mad_is_zero() {
+        let samples = vec![5.0, 5.0, 5.0, 5.0, 500.0];
+        let trimmed = Stats::trim_outliers(&samples, 3.0);
+        assert_eq!(trimmed, samples);
+    }
 }
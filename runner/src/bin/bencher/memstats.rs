@@ -0,0 +1,114 @@
+//! Per-phase memory footprint reporting via jemalloc's allocator stats.
+//!
+//! Gated behind the `mem-stats` cargo feature, which wires `jemallocator`
+//! as the global allocator. Without the feature, `snapshot` always returns
+//! `None` and `--mem-stats` is a no-op so the bencher still builds on
+//! platforms where jemalloc isn't available.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use color_print::ceprintln;
+
+/// How often the background sampler polls resident memory while a phase is
+/// in flight, to approximate its peak rather than just start/end.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A point-in-time read of jemalloc's allocator stats, in bytes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemSnapshot {
+    pub allocated: u64,
+    pub resident: u64,
+}
+
+/// Start/end snapshots plus the peak resident size observed in between.
+#[derive(Debug, Clone, Copy)]
+pub struct MemPhaseStats {
+    pub start: MemSnapshot,
+    pub end: MemSnapshot,
+    pub peak_resident: u64,
+}
+
+impl MemPhaseStats {
+    pub fn print(&self, phase: &str) {
+        ceprintln!(
+            "  <cyan>{:6}</>  mem allocated {:+.2} MiB ({:.2} -> {:.2})  resident {:+.2} MiB ({:.2} -> {:.2}, peak {:.2})",
+            format!("[{}]", phase),
+            bytes_to_mib(self.end.allocated as i64 - self.start.allocated as i64),
+            bytes_to_mib(self.start.allocated as i64),
+            bytes_to_mib(self.end.allocated as i64),
+            bytes_to_mib(self.end.resident as i64 - self.start.resident as i64),
+            bytes_to_mib(self.start.resident as i64),
+            bytes_to_mib(self.end.resident as i64),
+            bytes_to_mib(self.peak_resident as i64),
+        );
+    }
+}
+
+fn bytes_to_mib(bytes: i64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+#[cfg(feature = "mem-stats")]
+#[global_allocator]
+static GLOBAL: jemallocator::Jemalloc = jemallocator::Jemalloc;
+
+#[cfg(feature = "mem-stats")]
+pub fn snapshot() -> Option<MemSnapshot> {
+    jemalloc_ctl::epoch::advance().ok()?;
+    Some(MemSnapshot {
+        allocated: jemalloc_ctl::stats::allocated::read().ok()? as u64,
+        resident: jemalloc_ctl::stats::resident::read().ok()? as u64,
+    })
+}
+
+#[cfg(not(feature = "mem-stats"))]
+pub fn snapshot() -> Option<MemSnapshot> {
+    None
+}
+
+/// Run `f`, and if `enabled` and jemalloc stats are available, track
+/// allocated/resident memory across it: a start/end snapshot plus the peak
+/// resident size sampled every `SAMPLE_INTERVAL` while `f` runs.
+pub fn track<F, T>(enabled: bool, f: F) -> (T, Option<MemPhaseStats>)
+where
+    F: FnOnce() -> T,
+{
+    if !enabled {
+        return (f(), None);
+    }
+    let Some(start) = snapshot() else {
+        return (f(), None);
+    };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let peak_resident = Arc::new(AtomicU64::new(start.resident));
+    let sampler = {
+        let stop = stop.clone();
+        let peak_resident = peak_resident.clone();
+        thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                if let Some(s) = snapshot() {
+                    peak_resident.fetch_max(s.resident, Ordering::Relaxed);
+                }
+                thread::sleep(SAMPLE_INTERVAL);
+            }
+        })
+    };
+
+    let result = f();
+
+    stop.store(true, Ordering::Relaxed);
+    let _ = sampler.join();
+    let end = snapshot().unwrap_or(start);
+    (
+        result,
+        Some(MemPhaseStats {
+            start,
+            end,
+            peak_resident: peak_resident.load(Ordering::Relaxed),
+        }),
+    )
+}
@@ -0,0 +1,286 @@
+//! Native YCSB-style workload generation.
+//!
+//! Generates `KvCall`s in-process according to configurable operation-type
+//! proportions and a key-distribution selector, so benchmarking no longer
+//! depends on a Java/YCSB install or the hardcoded profile files under
+//! `ycsb/workloads/`.
+
+use std::collections::BTreeSet;
+
+use rand::rngs::ThreadRng;
+use rand::Rng;
+
+use runner::KvCall;
+
+/// Zipfian skew parameter used by the original YCSB core workloads.
+const ZIPFIAN_THETA: f64 = 0.99;
+
+/// Key-distribution selector for choosing which record a generated
+/// operation targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyDistribution {
+    /// Every key in range is equally likely.
+    Uniform,
+    /// Skewed towards low-numbered (earlier-inserted) keys.
+    Zipfian,
+    /// Skewed towards the most-recently-inserted key.
+    Latest,
+}
+
+/// Zipfian distribution generator over item indices `0..n`, using the
+/// standard YCSB/Gray inversion method. The normalization constant is
+/// precomputed once at construction.
+#[derive(Debug)]
+struct ZipfianGenerator {
+    n: usize,
+    theta: f64,
+    zeta: f64,
+    eta: f64,
+}
+
+impl ZipfianGenerator {
+    fn new(n: usize, theta: f64) -> Self {
+        let n = n.max(2);
+        let zeta2 = Self::zeta(2, theta);
+        let zeta = Self::zeta(n, theta);
+        let eta = (1.0 - (2.0 / n as f64).powf(1.0 - theta)) / (1.0 - zeta2 / zeta);
+        ZipfianGenerator {
+            n,
+            theta,
+            zeta,
+            eta,
+        }
+    }
+
+    /// `zeta(n, theta) = sum_{i=1}^{n} 1/i^theta`.
+    fn zeta(n: usize, theta: f64) -> f64 {
+        (1..=n).map(|i| 1.0 / (i as f64).powf(theta)).sum()
+    }
+
+    /// Draw an item index in `0..n` for a uniform sample `u in [0, 1)`.
+    fn next(&self, u: f64) -> usize {
+        let uz = u * self.zeta;
+        if uz < 1.0 {
+            return 0;
+        }
+        let idx = (self.n as f64) * (self.eta * u - self.eta + 1.0).powf(1.0 / (1.0 - self.theta));
+        (idx as usize).clamp(0, self.n - 1)
+    }
+}
+
+/// Configuration for an in-process YCSB-style workload: record/operation
+/// counts, per-op-type proportions, and a key-distribution selector.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub record_count: usize,
+    pub operation_count: usize,
+    pub read_prop: f64,
+    pub update_prop: f64,
+    pub insert_prop: f64,
+    pub scan_prop: f64,
+    pub rmw_prop: f64,
+    pub key_dist: KeyDistribution,
+}
+
+impl Workload {
+    /// Named workload presets 'a' through 'f', matching the operation-type
+    /// proportions of the original YCSB core workloads.
+    pub fn preset(workload: char, record_count: usize, operation_count: usize) -> Workload {
+        let (read_prop, update_prop, insert_prop, scan_prop, rmw_prop, key_dist) = match workload {
+            'a' => (0.5, 0.5, 0.0, 0.0, 0.0, KeyDistribution::Zipfian),
+            'b' => (0.95, 0.05, 0.0, 0.0, 0.0, KeyDistribution::Zipfian),
+            'c' => (1.0, 0.0, 0.0, 0.0, 0.0, KeyDistribution::Zipfian),
+            'd' => (0.95, 0.0, 0.05, 0.0, 0.0, KeyDistribution::Latest),
+            'e' => (0.0, 0.0, 0.05, 0.95, 0.0, KeyDistribution::Zipfian),
+            'f' => (0.5, 0.0, 0.0, 0.0, 0.5, KeyDistribution::Zipfian),
+            _ => unreachable!(),
+        };
+        Workload {
+            record_count,
+            operation_count,
+            read_prop,
+            update_prop,
+            insert_prop,
+            scan_prop,
+            rmw_prop,
+            key_dist,
+        }
+    }
+
+    /// Zero-padded, lexicographically-sortable key for record `i`.
+    fn make_key(i: usize) -> String {
+        format!("user{i:012}")
+    }
+
+    /// A synthetic value for inserts/updates, mirroring YCSB's random
+    /// field payload closely enough for benchmarking purposes.
+    fn make_value(rng: &mut ThreadRng) -> String {
+        let len = 100;
+        (0..len).map(|_| rng.gen_range('a'..='z')).collect()
+    }
+}
+
+/// Stateful generator that draws `KvCall`s from a `Workload` definition,
+/// tracking the growing key space as records are inserted.
+pub struct WorkloadGen {
+    workload: Workload,
+    rng: ThreadRng,
+    zipfian: ZipfianGenerator,
+}
+
+impl WorkloadGen {
+    pub fn new(workload: Workload) -> Self {
+        let zipfian = ZipfianGenerator::new(workload.record_count, ZIPFIAN_THETA);
+        WorkloadGen {
+            workload,
+            rng: rand::thread_rng(),
+            zipfian,
+        }
+    }
+
+    /// Generate the load-phase call for record `i`: a single INSERT.
+    pub fn next_load_call(&mut self, i: usize) -> (KvCall, &'static str) {
+        let key = Workload::make_key(i);
+        let value = Workload::make_value(&mut self.rng);
+        (KvCall::Put { key, value }, "INSERT")
+    }
+
+    /// Sample a key index into `0..present` according to the configured
+    /// key-distribution selector.
+    fn sample_key_idx(&mut self, present: usize) -> usize {
+        let present = present.max(1);
+        match self.workload.key_dist {
+            KeyDistribution::Uniform => self.rng.gen_range(0..present),
+            KeyDistribution::Zipfian => self.zipfian.next(self.rng.gen()).min(present - 1),
+            KeyDistribution::Latest => {
+                // skew towards the most-recently-inserted records by
+                // reusing the Zipfian draw over the distance from the end
+                let from_end = self.zipfian.next(self.rng.gen()).min(present - 1);
+                present - 1 - from_end
+            }
+        }
+    }
+
+    /// Generate the next run-phase call according to the configured
+    /// operation-type proportions, drawing its key from `ikeys`.
+    pub fn next_run_call(&mut self, ikeys: &BTreeSet<String>) -> (KvCall, &'static str) {
+        let present = ikeys.len();
+        let choice: f64 = self.rng.gen();
+        let w = &self.workload;
+        let thresholds = [
+            (w.read_prop, "READ"),
+            (w.update_prop, "UPDATE"),
+            (w.insert_prop, "INSERT"),
+            (w.scan_prop, "SCAN"),
+            (w.rmw_prop, "READMODIFYWRITE"),
+        ];
+
+        let mut acc = 0.0;
+        let mut op = thresholds.last().unwrap().1;
+        for (prop, name) in thresholds {
+            acc += prop;
+            if choice < acc {
+                op = name;
+                break;
+            }
+        }
+
+        match op {
+            "UPDATE" => {
+                let idx = self.sample_key_idx(present);
+                let key = ikeys
+                    .iter()
+                    .nth(idx)
+                    .cloned()
+                    .unwrap_or_else(|| Workload::make_key(0));
+                let value = Workload::make_value(&mut self.rng);
+                (KvCall::Swap { key, value }, "UPDATE")
+            }
+            "INSERT" => {
+                let key = Workload::make_key(self.workload.record_count + present);
+                let value = Workload::make_value(&mut self.rng);
+                (KvCall::Put { key, value }, "INSERT")
+            }
+            "SCAN" if ikeys.is_empty() => {
+                let key_start = Workload::make_key(0);
+                let key_end = key_start.clone();
+                (KvCall::Scan { key_start, key_end }, "SCAN")
+            }
+            "SCAN" => {
+                let idx = self.sample_key_idx(present);
+                let key_start = ikeys.iter().nth(idx).cloned().unwrap();
+                let scnt = self.rng.gen_range(1..=100);
+                let key_end = ikeys
+                    .range(key_start.clone()..)
+                    .nth(scnt - 1)
+                    .unwrap_or_else(|| ikeys.last().unwrap())
+                    .clone();
+                (KvCall::Scan { key_start, key_end }, "SCAN")
+            }
+            "READMODIFYWRITE" => {
+                let idx = self.sample_key_idx(present);
+                let key = ikeys
+                    .iter()
+                    .nth(idx)
+                    .cloned()
+                    .unwrap_or_else(|| Workload::make_key(0));
+                (KvCall::Get { key }, "READMODIFYWRITE")
+            }
+            _ => {
+                let idx = self.sample_key_idx(present);
+                let key = ikeys
+                    .iter()
+                    .nth(idx)
+                    .cloned()
+                    .unwrap_or_else(|| Workload::make_key(0));
+                (KvCall::Get { key }, "READ")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zipfian_spans_the_full_key_range() {
+        let n = 1000;
+        let zipf = ZipfianGenerator::new(n, ZIPFIAN_THETA);
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..50_000 {
+            let u = (i as f64 + 0.5) / 50_000.0;
+            seen.insert(zipf.next(u));
+        }
+        // a correct Zipfian draw over a smooth u sweep should land on a
+        // sizeable fraction of the key range, not collapse onto a handful
+        // of indices clustered away from the low end.
+        assert!(
+            seen.len() > n / 4,
+            "expected broad coverage of 0..{n}, got {} distinct indices",
+            seen.len()
+        );
+    }
+
+    #[test]
+    fn zipfian_favors_low_indices() {
+        let n = 1000;
+        let zipf = ZipfianGenerator::new(n, ZIPFIAN_THETA);
+        let mut rng = rand::thread_rng();
+        let mut low = 0;
+        let mut high = 0;
+        for _ in 0..20_000 {
+            if zipf.next(rng.gen()) < n / 10 {
+                low += 1;
+            } else {
+                high += 1;
+            }
+        }
+        // theta=0.99 is heavily skewed, so the bottom 10% of indices should
+        // draw the large majority of samples.
+        assert!(
+            low > high,
+            "expected low indices to dominate, got low={low} high={high}"
+        );
+    }
+}
@@ -2,79 +2,62 @@
 //!
 //! Our translation of YCSB operations to our KV operations do not strictly
 //! follow the original YCSB semantics, but are good enough for benchmarking.
+//!
+//! Workloads are generated in-process by `workload::WorkloadGen` (no
+//! external `ycsb.sh`/Java dependency); this module just drives a
+//! `ClientProc` with the generated calls and collects latency stats.
 
-use std::cell::RefCell;
-use std::collections::BTreeSet;
-use std::io::{BufRead, BufReader};
-use std::process::{Child, ChildStdout, Command, Stdio};
-use std::str::SplitWhitespace;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
-use runner::{ClientProc, KvCall, RunnerError};
-
-use crate::{Stats, RESP_TIMEOUT};
-
-thread_local! {
-    /// Thread-local buffer for reading lines of client output.
-    static READBUF: RefCell<String> = const { RefCell::new(String::new()) };
-}
+use runner::{ClientProc, RunnerError};
 
-/// Hardcoded paths of ycsb files:
-const YCSB_BIN: &str = "ycsb/bin/ycsb.sh";
+use crate::workload::{Workload, WorkloadGen};
+use crate::{Stats, RESP_TIMEOUT, STOP};
 
-const fn ycsb_profile(workload: char) -> &'static str {
-    match workload {
-        'a' => "ycsb/workloads/workloada",
-        'b' => "ycsb/workloads/workloadb",
-        'c' => "ycsb/workloads/workloadc",
-        'd' => "ycsb/workloads/workloadd",
-        'e' => "ycsb/workloads/workloade",
-        'f' => "ycsb/workloads/workloadf",
-        _ => unreachable!(),
-    }
+/// Open-loop pacing config: a target issue rate and a bounded in-flight
+/// window, used to avoid coordinated omission under slow responses.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OpenLoop {
+    pub(crate) target_rate: f64,
+    pub(crate) max_inflight: usize,
 }
 
-/// Wrapper handle to a YCSB basic driver process.
+/// Wrapper handle to a YCSB basic driver.
 #[derive(Debug)]
 pub struct YcsbDriver {
-    handle: Child,
     feeder: JoinHandle<Option<(Stats, BTreeSet<String>)>>,
     signal: mpsc::Receiver<()>, // for timeout
 }
 
 impl YcsbDriver {
-    /// Run a YCSB driver process that runs the specified workload for a number
-    /// of operations, returning a handle to it. The driver translates YCSB
-    /// output and feeds them directly into a KV client.
+    /// Run a YCSB driver that generates the specified workload for a number
+    /// of operations, returning a handle to it. The driver feeds generated
+    /// calls directly into a KV client.
     pub(crate) fn exec(
         workload: char,
         num_ops: usize,
         load: bool, // true if 'load', false if 'run'
         client: ClientProc,
         ikeys: BTreeSet<String>,
+        open_loop: Option<OpenLoop>,
     ) -> Result<YcsbDriver, RunnerError> {
-        let mut handle = Command::new(YCSB_BIN)
-            .arg(if load { "load" } else { "run" })
-            .arg("basic")
-            .arg("-P")
-            .arg(ycsb_profile(workload))
-            .arg("-p")
-            .arg(format!("operationcount={}", num_ops))
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .spawn()?;
-        let stdout = handle.stdout.take().unwrap();
+        let record_count = if load {
+            num_ops
+        } else {
+            ikeys.len().max(num_ops)
+        };
+        let gen = WorkloadGen::new(Workload::preset(workload, record_count, num_ops));
 
-        // spawn a translator & feeder thread that listens on the stdout of
-        // the basic driver, translates output lines into our KV operations,
-        // and feeds them to the KV client
         let (signal_tx, signal_rx) = mpsc::channel();
-        let feeder = thread::spawn(move || Self::feeder_thread(stdout, client, ikeys, signal_tx));
+        let feeder = thread::spawn(move || {
+            Self::feeder_thread(gen, num_ops, load, client, ikeys, open_loop, signal_tx)
+        });
 
         Ok(YcsbDriver {
-            handle,
             feeder,
             signal: signal_rx,
         })
@@ -83,185 +66,197 @@ impl YcsbDriver {
     /// Wait for the workload to finish, returning the statistics reported by
     /// the feeder thread and consuming self. If feeder failed, returns `None`.
     pub(crate) fn wait(
-        mut self,
+        self,
         timeout: Duration,
     ) -> Result<Option<(Stats, BTreeSet<String>)>, RunnerError> {
         self.signal.recv_timeout(timeout)?;
         let feeder_ret = self.feeder.join().map_err(|_| RunnerError::Join)?;
-
-        self.handle.kill()?;
         Ok(feeder_ret)
     }
 
-    /// Parse the key from YCSB call line.
-    fn parse_ycsb_key(segs: &mut SplitWhitespace) -> Result<String, RunnerError> {
-        let mut key = segs
-            .next()
-            .ok_or(RunnerError::Parse("missing key segment".into()))?
-            .to_string();
-        key.push('_');
-        key.push_str(
-            segs.next()
-                .ok_or(RunnerError::Parse("missing key segment".into()))?,
-        );
-        Ok(key)
+    /// Generate and feed a single operation to the KV client, recording its
+    /// latency under `op` into `stats`. READMODIFYWRITE is a read followed
+    /// by a write of the same key, timed as one combined operation.
+    fn feed_a_call(
+        client: &mut ClientProc,
+        gen: &mut WorkloadGen,
+        ikeys: &mut BTreeSet<String>,
+        load: bool,
+        i: usize,
+        stats: &mut Stats,
+    ) -> Result<(), RunnerError> {
+        let (call, op) = if load {
+            gen.next_load_call(i)
+        } else {
+            gen.next_run_call(ikeys)
+        };
+
+        if let runner::KvCall::Put { key, .. } = &call {
+            ikeys.insert(key.clone());
+        }
+        // for RMW, the write must land on the same key that was just read
+        let rmw_key = match (&call, op) {
+            (runner::KvCall::Get { key }, "READMODIFYWRITE") => Some(key.clone()),
+            _ => None,
+        };
+
+        let start = Instant::now();
+        client.send_call(call)?;
+        let _ = client.wait_resp(RESP_TIMEOUT)?;
+
+        if let Some(key) = rmw_key {
+            let value = "modified".to_string();
+            client.send_call(runner::KvCall::Swap { key, value })?;
+            let _ = client.wait_resp(RESP_TIMEOUT)?;
+        }
+
+        let elapsed = start.elapsed();
+        stats.record_op(op, elapsed.as_micros() as f64);
+        Ok(())
     }
 
-    /// Parse the square-bracketed "value" from YCSB call line.
-    fn parse_ycsb_value(segs: &mut SplitWhitespace) -> Result<String, RunnerError> {
-        if segs.next() != Some("[") {
-            return Err(RunnerError::Parse("no value start bracket".into()));
+    /// Issue a single operation without waiting for its response, returning
+    /// the op name and the number of responses to drain for it.
+    /// READMODIFYWRITE issues both its read and its follow-up write of the
+    /// same key up front, so the caller must drain two responses.
+    fn issue_a_call(
+        client: &mut ClientProc,
+        gen: &mut WorkloadGen,
+        ikeys: &mut BTreeSet<String>,
+        load: bool,
+        i: usize,
+    ) -> Result<(&'static str, usize), RunnerError> {
+        let (call, op) = if load {
+            gen.next_load_call(i)
+        } else {
+            gen.next_run_call(ikeys)
+        };
+        if let runner::KvCall::Put { key, .. } = &call {
+            ikeys.insert(key.clone());
         }
-        let mut value = String::new();
-        for seg in segs {
-            value.push('_'); // substitute space with '_'
-            value.push_str(seg);
+        let rmw_key = match (&call, op) {
+            (runner::KvCall::Get { key }, "READMODIFYWRITE") => Some(key.clone()),
+            _ => None,
+        };
+
+        client.send_call(call)?;
+        if let Some(key) = rmw_key {
+            let value = "modified".to_string();
+            client.send_call(runner::KvCall::Swap { key, value })?;
+            Ok((op, 2))
+        } else {
+            Ok((op, 1))
         }
-        Ok(value)
     }
 
-    /// Parse the scan keys count from YCSB call line.
-    fn parse_ycsb_scnt(segs: &mut SplitWhitespace) -> Result<usize, RunnerError> {
-        let scnt = segs
-            .next()
-            .ok_or(RunnerError::Parse("missing scan count".into()))?
-            .parse::<usize>()?;
-        Ok(scnt)
+    /// Wait for the oldest outstanding open-loop call to complete and record
+    /// its coordinated-omission-corrected latency: the larger of the delay
+    /// since its intended (scheduled) start and since its actual issue time,
+    /// so a call that couldn't even be sent on time still counts in full.
+    fn drain_oldest(
+        client: &mut ClientProc,
+        inflight: &mut VecDeque<(Instant, Instant, &'static str, usize)>,
+        stats: &mut Stats,
+    ) -> Result<(), RunnerError> {
+        let (intended_start, actual_start, op, num_resps) =
+            inflight.pop_front().expect("inflight is non-empty");
+        for _ in 0..num_resps {
+            let _ = client.wait_resp(RESP_TIMEOUT)?;
+        }
+        let actual_end = Instant::now();
+        let latency = (actual_end - intended_start).max(actual_end - actual_start);
+        stats.record_op(op, latency.as_micros() as f64);
+        Ok(())
     }
 
-    /// Parse a YCSB driver output line into a KV operation call, or `None` if
-    /// not a call line.
-    fn interpret_ycsb_call(
-        line: &str,
+    /// Open-loop feeder loop: paces issuance against `base + i/target_rate`
+    /// (sleeping only when ahead of schedule) and pipelines up to
+    /// `max_inflight` outstanding operations through `client`, so slow responses
+    /// throttle neither the offered load nor the reported tail latency.
+    fn feed_open_loop(
+        client: &mut ClientProc,
+        gen: &mut WorkloadGen,
         ikeys: &mut BTreeSet<String>,
-    ) -> Result<Option<(KvCall, String)>, RunnerError> {
-        let mut segs = line.split_whitespace();
-        match segs.next() {
-            Some("INSERT") => {
-                let key = Self::parse_ycsb_key(&mut segs)?;
-                let value = Self::parse_ycsb_value(&mut segs)?;
-                ikeys.insert(key.clone());
-                Ok(Some((KvCall::Put { key, value }, "INSERT".into())))
-            }
+        load: bool,
+        num_ops: usize,
+        open_loop: OpenLoop,
+        stats: &mut Stats,
+    ) -> Result<(), RunnerError> {
+        let base = Instant::now();
+        let mut inflight = VecDeque::with_capacity(open_loop.max_inflight);
 
-            Some("UPDATE") => {
-                let key = Self::parse_ycsb_key(&mut segs)?;
-                let value = Self::parse_ycsb_value(&mut segs)?;
-                Ok(Some((KvCall::Swap { key, value }, "UPDATE".into())))
+        for i in 0..num_ops {
+            if STOP.load(Ordering::Relaxed) {
+                break;
             }
 
-            Some("READ") => {
-                let key = Self::parse_ycsb_key(&mut segs)?;
-                Ok(Some((KvCall::Get { key }, "READ".into())))
+            let intended_start = base + Duration::from_secs_f64(i as f64 / open_loop.target_rate);
+            let now = Instant::now();
+            if now < intended_start {
+                thread::sleep(intended_start - now);
             }
 
-            Some("SCAN") => {
-                let key_start = Self::parse_ycsb_key(&mut segs)?;
-                let key_end = if ikeys.is_empty() {
-                    "zzzzzzzz".into()
-                } else {
-                    let scnt = Self::parse_ycsb_scnt(&mut segs)?;
-                    ikeys
-                        .range(key_start.clone()..)
-                        .nth(scnt - 1)
-                        .unwrap_or(ikeys.last().unwrap())
-                        .clone()
-                };
-                Ok(Some((KvCall::Scan { key_start, key_end }, "SCAN".into())))
-            }
+            let (op, num_resps) = Self::issue_a_call(client, gen, ikeys, load, i)?;
+            inflight.push_back((intended_start, Instant::now(), op, num_resps));
 
-            // no Deletes in default YCSB
-            _ => Ok(None),
+            if inflight.len() >= open_loop.max_inflight {
+                Self::drain_oldest(client, &mut inflight, stats)?;
+            }
+        }
+        while !inflight.is_empty() {
+            Self::drain_oldest(client, &mut inflight, stats)?;
         }
+        Ok(())
     }
 
-    /// Feed a line of YCSB basic driver output to the KV client. For the last
-    /// few performance reporting lines, this function records the performance
-    /// numbers into `stats`.
-    fn feed_a_line(
-        stdout: &mut BufReader<ChildStdout>,
+    /// Closed-loop feeder loop: one call at a time, blocking on its response
+    /// before issuing the next.
+    fn feed_closed_loop(
         client: &mut ClientProc,
-        line: &mut String,
+        gen: &mut WorkloadGen,
         ikeys: &mut BTreeSet<String>,
+        load: bool,
+        num_ops: usize,
         stats: &mut Stats,
-        ended: &mut bool,
-        started: &mut Option<Instant>,
-        last_end: &mut Option<Instant>,
     ) -> Result<(), RunnerError> {
-        line.clear();
-
-        let size = stdout.read_line(line)?;
-        if size == 0 {
-            // EOF reached, workload completed
-            *ended = true;
-            return Ok(());
-        }
-        if line.trim().is_empty() {
-            // skip empty line
-            return Ok(());
-        }
-
-        if let Some((call, op)) = Self::interpret_ycsb_call(line, ikeys)? {
-            // is an operation call, do it synchronously
-            // RESP_TIMEOUT should be long enough to prevent false negatives
-            let start = Instant::now();
-            client.send_call(call)?;
-            let _ = client.wait_resp(RESP_TIMEOUT)?;
-            let elapsed = start.elapsed();
-
-            if started.is_none() {
-                *started = Some(start);
+        for i in 0..num_ops {
+            if STOP.load(Ordering::Relaxed) {
+                break;
             }
-            *last_end = Some(Instant::now());
-            stats.record_op(&op, elapsed.as_micros() as f64);
-        } else if line.starts_with('[') {
-            // performance reporting line from YCSB, ignored
-        } else if line.contains("No such file") {
-            // probably not finding the workload profile file
-            return Err(RunnerError::Io(line.clone()));
+            Self::feed_a_call(client, gen, ikeys, load, i, stats)?;
         }
-
         Ok(())
     }
 
-    /// Translator & feeder thread function. Returns a tuple of statistics
+    /// Generator & feeder thread function. Returns a tuple of statistics
     /// collected and sorted list of keys inserted on success.
     fn feeder_thread(
-        stdout: ChildStdout,
+        mut gen: WorkloadGen,
+        num_ops: usize,
+        load: bool,
         mut client: ClientProc,
         mut ikeys: BTreeSet<String>,
+        open_loop: Option<OpenLoop>,
         signal: mpsc::Sender<()>,
     ) -> Option<(Stats, BTreeSet<String>)> {
         let mut stats = Stats::new();
-        let mut ended = false;
-        let mut started: Option<Instant> = None;
-        let mut last_end: Option<Instant> = None;
+        let started = Instant::now();
 
-        READBUF.with(|buf| {
-            let line = &mut buf.borrow_mut();
-            let mut stdout = BufReader::new(stdout);
-
-            loop {
-                if let Err(err) = Self::feed_a_line(
-                    &mut stdout,
-                    &mut client,
-                    line,
-                    &mut ikeys,
-                    &mut stats,
-                    &mut ended,
-                    &mut started,
-                    &mut last_end,
-                ) {
-                    if !matches!(err, RunnerError::Chan(_)) {
-                        eprintln!("Error in feeder: {}", err);
-                    }
-                    break;
-                }
-                if ended {
-                    break;
-                }
+        let result = match open_loop {
+            Some(open_loop) => Self::feed_open_loop(
+                &mut client,
+                &mut gen,
+                &mut ikeys,
+                load,
+                num_ops,
+                open_loop,
+                &mut stats,
+            ),
+            None => {
+                Self::feed_closed_loop(&mut client, &mut gen, &mut ikeys, load, num_ops, &mut stats)
             }
-        });
+        };
+        stats.total_ms = started.elapsed().as_secs_f64() * 1000.0;
 
         // stop the client process
         if let Err(err) = client.stop() {
@@ -269,16 +264,17 @@ impl YcsbDriver {
         }
 
         let _ = signal.send(()); // for timeout
-        if !ended {
-            // error in workload feeding
-            None
-        } else {
-            // ended successfully
-            if let (Some(start), Some(end)) = (started, last_end) {
-                stats.total_ms = (end - start).as_secs_f64() * 1000.0;
+        match result {
+            Ok(()) => {
+                stats.merged = 1;
+                Some((stats, ikeys))
+            }
+            Err(err) => {
+                if !matches!(err, RunnerError::Chan(_)) {
+                    eprintln!("Error in feeder: {}", err);
+                }
+                None
             }
-            stats.merged = 1;
-            Some((stats, ikeys))
         }
     }
 }